@@ -1,15 +1,277 @@
+use std::collections::HashSet;
+
 use proc_macro2::TokenStream;
 use quote::quote;
+use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
-use syn::{parse2, parse_quote, Data, DeriveInput, Error, Fields, Result};
+use syn::{
+    parse2, parse_quote, Attribute, Data, DeriveInput, Error, Expr, ExprLit, Fields,
+    GenericArgument, Ident, Lit, LitInt, LitStr, Meta, PathArguments, Result, Token, Type,
+    WherePredicate,
+};
 
 use crate::{
     add_trait_bounds, decode_split_for_impl, find_packet_id_attr, pair_variants_with_discriminants,
 };
 
+/// The parsed contents of a field or container's `#[decode(...)]` helper
+/// attributes.
+#[derive(Default)]
+struct DecodeAttrs {
+    /// Predicates from `#[decode(bound = "...")]`.
+    bound: Vec<WherePredicate>,
+    /// The expression from `#[decode(if = "...")]`, if present.
+    cond: Option<Expr>,
+}
+
+/// Parses every `#[decode(...)]` helper attribute in `attrs`.
+fn parse_decode_attrs(attrs: &[Attribute]) -> Result<DecodeAttrs> {
+    let mut out = DecodeAttrs::default();
+
+    for attr in attrs {
+        if !attr.path().is_ident("decode") {
+            continue;
+        }
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("bound") {
+                let lit: LitStr = meta.value()?.parse()?;
+                out.bound.extend(
+                    lit.parse_with(Punctuated::<WherePredicate, Token![,]>::parse_terminated)?,
+                );
+                Ok(())
+            } else if meta.path.is_ident("if") {
+                let lit: LitStr = meta.value()?.parse()?;
+                out.cond = Some(lit.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unrecognized `decode` helper attribute"))
+            }
+        })?;
+    }
+
+    Ok(out)
+}
+
+/// `true` if `attrs` contains a bare `#[skip]` helper attribute.
+fn is_skip(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident("skip"))
+}
+
+/// Errors out if `attrs` carries a `#[decode(if = ...)]` condition. Only a
+/// named struct field can be decoded into a `let` binding that a later
+/// field's condition can refer back to, so the attribute is rejected
+/// everywhere else instead of being silently ignored.
+fn reject_decode_if(attrs: &[Attribute]) -> Result<()> {
+    if let Some(cond) = parse_decode_attrs(attrs)?.cond {
+        return Err(Error::new(
+            cond.span(),
+            "`#[decode(if = ...)]` is only supported on named struct fields",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Collects every identifier in `type_params` that a `#[skip]`ped field's
+/// `Default::default()` call would need a `T: Default` bound for -- either
+/// used bare (`T`) or nested in a fixed-size array or tuple (`[T; N]`,
+/// `(T, U)`), whose own `Default` impl requires it of their elements. A
+/// generic container like `Vec<T>` is deliberately not descended into here:
+/// its `Default` impl doesn't require `T: Default`.
+fn collect_default_idents(ty: &Type, type_params: &HashSet<Ident>, out: &mut HashSet<Ident>) {
+    match ty {
+        Type::Path(ty) => {
+            if let Some(ident) = ty.path.get_ident() {
+                if type_params.contains(ident) {
+                    out.insert(ident.clone());
+                }
+            }
+        }
+        Type::Array(ty) => collect_default_idents(&ty.elem, type_params, out),
+        Type::Tuple(ty) => {
+            for elem in &ty.elems {
+                collect_default_idents(elem, type_params, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Collects every identifier in `type_params` that appears anywhere in `ty`
+/// into `out` -- not just as `ty` itself, but also nested inside containers
+/// like `Vec<T>`, `Option<T>`, tuples, arrays, and references. A type
+/// parameter reached this way is genuinely decoded by the generated code, so
+/// it must keep its `Decode` bound even if some *other* field skips it.
+fn collect_type_params(ty: &Type, type_params: &HashSet<Ident>, out: &mut HashSet<Ident>) {
+    match ty {
+        Type::Path(ty) => {
+            if let Some(ident) = ty.path.get_ident() {
+                if type_params.contains(ident) {
+                    out.insert(ident.clone());
+                }
+            }
+
+            for seg in &ty.path.segments {
+                if let PathArguments::AngleBracketed(args) = &seg.arguments {
+                    for arg in &args.args {
+                        if let GenericArgument::Type(ty) = arg {
+                            collect_type_params(ty, type_params, out);
+                        }
+                    }
+                }
+            }
+        }
+        Type::Reference(ty) => collect_type_params(&ty.elem, type_params, out),
+        Type::Array(ty) => collect_type_params(&ty.elem, type_params, out),
+        Type::Slice(ty) => collect_type_params(&ty.elem, type_params, out),
+        Type::Group(ty) => collect_type_params(&ty.elem, type_params, out),
+        Type::Paren(ty) => collect_type_params(&ty.elem, type_params, out),
+        Type::Tuple(ty) => {
+            for elem in &ty.elems {
+                collect_type_params(elem, type_params, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Given every field's `(is_skip, field type)` pair, works out which of
+/// `type_params` need a `Default` bound (because a `#[skip]`ped field uses
+/// them directly) and which of those can also be excluded from the
+/// automatically generated `Decode` bound (because *every* field that
+/// actually decodes them -- including uses nested inside containers like
+/// `Vec<T>` -- is itself skipped, so no generated code actually needs `T:
+/// Decode`).
+fn skip_bound_info(
+    type_params: &HashSet<Ident>,
+    fields: impl Iterator<Item = (bool, Type)>,
+) -> (HashSet<Ident>, Vec<WherePredicate>) {
+    let mut skipped = HashSet::new();
+    let mut decoded = HashSet::new();
+
+    for (skip, ty) in fields {
+        if skip {
+            collect_default_idents(&ty, type_params, &mut skipped);
+        } else {
+            collect_type_params(&ty, type_params, &mut decoded);
+        }
+    }
+
+    let excludes = skipped.difference(&decoded).cloned().collect();
+    let predicates = skipped
+        .iter()
+        .map(|ident| parse_quote!(#ident: ::std::default::Default))
+        .collect();
+
+    (excludes, predicates)
+}
+
+/// `true` if `ty` is (syntactically) `Option<_>`.
+fn is_option_type(ty: &Type) -> bool {
+    match ty {
+        Type::Path(ty) => ty
+            .path
+            .segments
+            .last()
+            .is_some_and(|seg| seg.ident == "Option"),
+        _ => false,
+    }
+}
+
+/// The type parameter a `where` predicate constrains, if it's a simple
+/// `Ident: ...` predicate naming one directly.
+fn bound_target_ident(pred: &WherePredicate) -> Option<Ident> {
+    match pred {
+        WherePredicate::Type(pred) => match &pred.bounded_ty {
+            Type::Path(ty) => ty.path.get_ident().cloned(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Gathers `#[decode(bound = "...")]` predicates from `container_attrs` and
+/// every field's attributes, returning both the predicates themselves and
+/// the set of type parameters they cover. Parameters in that set are
+/// excluded from the automatically generated `Decode` bound, since the
+/// caller has supplied their own constraint instead.
+fn collect_decode_bounds(
+    container_attrs: &[Attribute],
+    field_attrs: impl Iterator<Item = Vec<Attribute>>,
+) -> Result<(Vec<WherePredicate>, HashSet<Ident>)> {
+    let mut predicates = parse_decode_attrs(container_attrs)?.bound;
+
+    for attrs in field_attrs {
+        predicates.extend(parse_decode_attrs(&attrs)?.bound);
+    }
+
+    let excluded = predicates.iter().filter_map(bound_target_ident).collect();
+
+    Ok((predicates, excluded))
+}
+
+/// The primitive integer types `#[repr(...)]` / `#[tag_type = ...]` may name
+/// as an enum's discriminant encoding.
+const TAG_TYPES: &[&str] = &["u8", "u16", "u32", "i8", "i16", "i32"];
+
+/// Looks for a `#[repr(u8|u16|u32|i8|i16|i32)]` attribute, or the dedicated
+/// `#[tag_type = "u8"]` helper attribute, and returns the named integer
+/// type. Returns `None` when neither is present, in which case the
+/// discriminant is encoded as a `VarInt`.
+fn find_tag_type(attrs: &[Attribute]) -> Result<Option<Ident>> {
+    for attr in attrs {
+        if attr.path().is_ident("repr") {
+            let ident: Ident = attr.parse_args()?;
+            if TAG_TYPES.contains(&ident.to_string().as_str()) {
+                return Ok(Some(ident));
+            }
+        } else if attr.path().is_ident("tag_type") {
+            let Meta::NameValue(nv) = &attr.meta else {
+                return Err(Error::new(attr.span(), "expected `#[tag_type = \"...\"]`"));
+            };
+
+            let Expr::Lit(ExprLit {
+                lit: Lit::Str(lit), ..
+            }) = &nv.value
+            else {
+                return Err(Error::new(
+                    nv.value.span(),
+                    "tag type must be a string literal naming an integer type",
+                ));
+            };
+
+            let ident: Ident = lit.parse()?;
+
+            if !TAG_TYPES.contains(&ident.to_string().as_str()) {
+                return Err(Error::new(ident.span(), "unsupported tag type"));
+            }
+
+            return Ok(Some(ident));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Renders `disc` as an integer literal suffixed with `tag_type` (e.g.
+/// `0u8`), or as a plain `i32` literal when there is no `tag_type`, so the
+/// discriminant in a match arm has the same type as the value being
+/// matched.
+fn disc_literal(disc: i32, tag_type: Option<&Ident>) -> Result<LitInt> {
+    let repr = match tag_type {
+        Some(ty) => format!("{disc}{ty}"),
+        None => disc.to_string(),
+    };
+
+    syn::parse_str(&repr)
+}
+
 pub fn derive_decode(item: TokenStream) -> Result<TokenStream> {
     let mut input = parse2::<DeriveInput>(item)?;
 
+    reject_decode_if(&input.attrs)?;
+
     let name = input.ident;
 
     if input.generics.lifetimes().count() > 1 {
@@ -30,31 +292,120 @@ pub fn derive_decode(item: TokenStream) -> Result<TokenStream> {
 
     match input.data {
         Data::Struct(struct_) => {
+            let field_attrs: Vec<Vec<Attribute>> = match &struct_.fields {
+                Fields::Named(fields) => fields.named.iter().map(|f| f.attrs.clone()).collect(),
+                Fields::Unnamed(fields) => fields.unnamed.iter().map(|f| f.attrs.clone()).collect(),
+                Fields::Unit => Vec::new(),
+            };
+
+            let (mut bound_predicates, mut bound_excludes) =
+                collect_decode_bounds(&input.attrs, field_attrs.into_iter())?;
+
+            // A `#[skip]`ped field whose type is a bare generic parameter (or an
+            // array or tuple of one) needs a `Default` bound. That parameter can
+            // also be excluded from the automatically generated `Decode` bound, but
+            // only if every field using it is skipped -- it may still be decoded by
+            // a normal field, possibly nested inside a container like `Vec<T>`.
+            let type_params: HashSet<Ident> = input
+                .generics
+                .type_params()
+                .map(|param| param.ident.clone())
+                .collect();
+
+            let skip_fields: Vec<(bool, Type)> = match &struct_.fields {
+                Fields::Named(fields) => fields
+                    .named
+                    .iter()
+                    .map(|f| (is_skip(&f.attrs), f.ty.clone()))
+                    .collect(),
+                Fields::Unnamed(fields) => fields
+                    .unnamed
+                    .iter()
+                    .map(|f| (is_skip(&f.attrs), f.ty.clone()))
+                    .collect(),
+                Fields::Unit => Vec::new(),
+            };
+
+            let (skip_excludes, skip_predicates) =
+                skip_bound_info(&type_params, skip_fields.into_iter());
+            bound_excludes.extend(skip_excludes);
+            bound_predicates.extend(skip_predicates);
+
             let decode_fields = match struct_.fields {
                 Fields::Named(fields) => {
-                    let init = fields.named.iter().map(|f| {
+                    // Fields are decoded into `let` bindings first (rather than directly
+                    // into the `Self { .. }` literal) so that a `#[decode(if = ...)]`
+                    // condition can refer to an already-decoded field by name.
+                    let mut field_lets = Vec::new();
+                    let mut field_names = Vec::new();
+
+                    for f in &fields.named {
                         let name = f.ident.as_ref().unwrap();
+                        let ty = &f.ty;
                         let ctx = format!("failed to decode field `{name}`");
-                        quote! {
-                            #name: Decode::decode(_r).context(#ctx)?,
-                        }
-                    });
+
+                        let cond = parse_decode_attrs(&f.attrs)?.cond;
+
+                        let field_let = if is_skip(&f.attrs) {
+                            if let Some(cond) = cond {
+                                return Err(Error::new(
+                                    cond.span(),
+                                    "`#[decode(if = ...)]` has no effect on a `#[skip]`ped field",
+                                ));
+                            }
+
+                            quote! {
+                                let #name: #ty = Default::default();
+                            }
+                        } else if let Some(cond) = cond {
+                            if !is_option_type(ty) {
+                                return Err(Error::new(
+                                    ty.span(),
+                                    "field annotated with `#[decode(if = ...)]` must have type `Option<T>`",
+                                ));
+                            }
+
+                            quote! {
+                                let #name: #ty = if #cond {
+                                    Some(Decode::decode(_r).context(#ctx)?)
+                                } else {
+                                    None
+                                };
+                            }
+                        } else {
+                            quote! {
+                                let #name: #ty = Decode::decode(_r).context(#ctx)?;
+                            }
+                        };
+
+                        field_lets.push(field_let);
+                        field_names.push(name);
+                    }
 
                     quote! {
+                        #(#field_lets)*
                         Self {
-                            #(#init)*
+                            #(#field_names),*
                         }
                     }
                 }
                 Fields::Unnamed(fields) => {
-                    let init = (0..fields.unnamed.len())
-                        .map(|i| {
+                    let init = fields
+                        .unnamed
+                        .iter()
+                        .enumerate()
+                        .map(|(i, f)| {
+                            reject_decode_if(&f.attrs)?;
+
                             let ctx = format!("failed to decode field `{i}`");
-                            quote! {
-                                Decode::decode(_r).context(#ctx)?,
-                            }
+
+                            Ok(if is_skip(&f.attrs) {
+                                quote!(Default::default(),)
+                            } else {
+                                quote!(Decode::decode(_r).context(#ctx)?,)
+                            })
                         })
-                        .collect::<TokenStream>();
+                        .collect::<Result<TokenStream>>()?;
 
                     quote! {
                         Self(#init)
@@ -66,10 +417,11 @@ pub fn derive_decode(item: TokenStream) -> Result<TokenStream> {
             add_trait_bounds(
                 &mut input.generics,
                 quote!(::valence_protocol::Decode<#lifetime>),
+                &bound_excludes,
             );
 
             let (impl_generics, ty_generics, where_clause) =
-                decode_split_for_impl(input.generics, lifetime.clone());
+                decode_split_for_impl(input.generics, lifetime.clone(), bound_predicates);
 
             Ok(quote! {
                 #[allow(unused_imports)]
@@ -79,67 +431,183 @@ pub fn derive_decode(item: TokenStream) -> Result<TokenStream> {
                     fn decode(_r: &mut &#lifetime [u8]) -> ::valence_protocol::__private::Result<Self> {
                         use ::valence_protocol::__private::{Decode, Context, ensure};
 
-                        Ok(#decode_fields)
+                        Ok({ #decode_fields })
                     }
                 }
             })
         }
         Data::Enum(enum_) => {
-            let variants = pair_variants_with_discriminants(enum_.variants.into_iter())?;
+            let field_attrs: Vec<Vec<Attribute>> = enum_
+                .variants
+                .iter()
+                .flat_map(|variant| match &variant.fields {
+                    Fields::Named(fields) => fields.named.iter().map(|f| f.attrs.clone()).collect(),
+                    Fields::Unnamed(fields) => {
+                        fields.unnamed.iter().map(|f| f.attrs.clone()).collect()
+                    }
+                    Fields::Unit => Vec::new(),
+                })
+                .collect();
+
+            let (mut bound_predicates, mut bound_excludes) =
+                collect_decode_bounds(&input.attrs, field_attrs.into_iter())?;
+
+            let type_params: HashSet<Ident> = input
+                .generics
+                .type_params()
+                .map(|param| param.ident.clone())
+                .collect();
+
+            let skip_fields: Vec<(bool, Type)> = enum_
+                .variants
+                .iter()
+                .flat_map(|variant| match &variant.fields {
+                    Fields::Named(fields) => fields
+                        .named
+                        .iter()
+                        .map(|f| (is_skip(&f.attrs), f.ty.clone()))
+                        .collect(),
+                    Fields::Unnamed(fields) => fields
+                        .unnamed
+                        .iter()
+                        .map(|f| (is_skip(&f.attrs), f.ty.clone()))
+                        .collect(),
+                    Fields::Unit => Vec::new(),
+                })
+                .collect();
+
+            let (skip_excludes, skip_predicates) =
+                skip_bound_info(&type_params, skip_fields.into_iter());
+            bound_excludes.extend(skip_excludes);
+            bound_predicates.extend(skip_predicates);
+
+            let tag_type = find_tag_type(&input.attrs)?;
+
+            let mut fallback_variant = None;
+            let mut regular_variants = Vec::new();
+
+            for variant in enum_.variants {
+                if variant.attrs.iter().any(|a| a.path().is_ident("fallback")) {
+                    if fallback_variant.is_some() {
+                        return Err(Error::new(
+                            variant.ident.span(),
+                            "at most one `#[fallback]` variant may be declared",
+                        ));
+                    }
+                    fallback_variant = Some(variant);
+                } else {
+                    regular_variants.push(variant);
+                }
+            }
+
+            let variants = pair_variants_with_discriminants(regular_variants.into_iter())?;
 
             let decode_arms = variants
                 .iter()
                 .map(|(disc, variant)| {
                     let name = &variant.ident;
+                    let disc = disc_literal(*disc, tag_type.as_ref())?;
 
-                    match &variant.fields {
+                    Ok(match &variant.fields {
                         Fields::Named(fields) => {
                             let fields = fields
                                 .named
                                 .iter()
                                 .map(|f| {
+                                    reject_decode_if(&f.attrs)?;
+
                                     let field = f.ident.as_ref().unwrap();
                                     let ctx = format!(
                                         "failed to decode field `{field}` in variant `{name}`",
                                     );
-                                    quote! {
-                                        #field: Decode::decode(_r).context(#ctx)?,
-                                    }
+
+                                    Ok(if is_skip(&f.attrs) {
+                                        quote!(#field: Default::default(),)
+                                    } else {
+                                        quote! {
+                                            #field: Decode::decode(_r).context(#ctx)?,
+                                        }
+                                    })
                                 })
-                                .collect::<TokenStream>();
+                                .collect::<Result<TokenStream>>()?;
 
                             quote! {
                                 #disc => Ok(Self::#name { #fields }),
                             }
                         }
                         Fields::Unnamed(fields) => {
-                            let init = (0..fields.unnamed.len())
-                                .map(|i| {
+                            let init = fields
+                                .unnamed
+                                .iter()
+                                .enumerate()
+                                .map(|(i, f)| {
+                                    reject_decode_if(&f.attrs)?;
+
                                     let ctx = format!(
                                         "failed to decode field `{i}` in variant `{name}`",
                                     );
-                                    quote! {
-                                        Decode::decode(_r).context(#ctx)?,
-                                    }
+
+                                    Ok(if is_skip(&f.attrs) {
+                                        quote!(Default::default(),)
+                                    } else {
+                                        quote!(Decode::decode(_r).context(#ctx)?,)
+                                    })
                                 })
-                                .collect::<TokenStream>();
+                                .collect::<Result<TokenStream>>()?;
 
                             quote! {
                                 #disc => Ok(Self::#name(#init)),
                             }
                         }
                         Fields::Unit => quote!(#disc => Ok(Self::#name),),
-                    }
+                    })
                 })
-                .collect::<TokenStream>();
+                .collect::<Result<TokenStream>>()?;
+
+            let fallback_arm =
+                match &fallback_variant {
+                    Some(variant) => {
+                        let name = &variant.ident;
+
+                        match &variant.fields {
+                            Fields::Unit => quote!(_ => Ok(Self::#name),),
+                            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                                let field_ty = &fields.unnamed[0].ty;
+                                quote!(_ => Ok(Self::#name(disc as #field_ty)),)
+                            }
+                            _ => return Err(Error::new(
+                                variant.fields.span(),
+                                "`#[fallback]` variant must be a unit variant or a single-field \
+                                 tuple variant",
+                            )),
+                        }
+                    }
+                    None => quote!(n => bail!("unexpected enum discriminant {}", n),),
+                };
 
             add_trait_bounds(
                 &mut input.generics,
                 quote!(::valence_protocol::Decode<#lifetime>),
+                &bound_excludes,
             );
 
             let (impl_generics, ty_generics, where_clause) =
-                decode_split_for_impl(input.generics, lifetime.clone());
+                decode_split_for_impl(input.generics, lifetime.clone(), bound_predicates);
+
+            let (disc_decode, disc_ty) = match &tag_type {
+                Some(ty) => (
+                    quote!(#ty::decode(_r).context("failed to decode enum discriminant")?),
+                    quote!(#ty),
+                ),
+                None => (
+                    quote!(
+                        VarInt::decode(_r)
+                            .context("failed to decode enum discriminant")?
+                            .0
+                    ),
+                    quote!(i32),
+                ),
+            };
 
             Ok(quote! {
                 #[allow(unused_imports)]
@@ -149,10 +617,10 @@ pub fn derive_decode(item: TokenStream) -> Result<TokenStream> {
                     fn decode(_r: &mut &#lifetime [u8]) -> ::valence_protocol::__private::Result<Self> {
                         use ::valence_protocol::__private::{Decode, Context, VarInt, bail};
 
-                        let disc = VarInt::decode(_r).context("failed to decode enum discriminant")?.0;
+                        let disc: #disc_ty = #disc_decode;
                         match disc {
                             #decode_arms
-                            n => bail!("unexpected enum discriminant {}", disc),
+                            #fallback_arm
                         }
                     }
                 }
@@ -166,13 +634,13 @@ pub fn derive_decode(item: TokenStream) -> Result<TokenStream> {
 }
 
 pub fn derive_decode_packet(item: TokenStream) -> Result<TokenStream> {
-    let mut input = parse2::<DeriveInput>(item)?;
+    let input = parse2::<DeriveInput>(item)?;
 
     let Some(packet_id) = find_packet_id_attr(&input.attrs)? else {
         return Err(Error::new(
             input.ident.span(),
             "cannot derive `DecodePacket` without `#[packet_id = ...]` helper attribute",
-        ))
+        ));
     };
 
     let lifetime = input
@@ -182,13 +650,16 @@ pub fn derive_decode_packet(item: TokenStream) -> Result<TokenStream> {
         .map(|l| l.lifetime.clone())
         .unwrap_or_else(|| parse_quote!('a));
 
-    add_trait_bounds(
-        &mut input.generics,
-        quote!(::valence_protocol::__private::Decode<#lifetime>),
-    );
+    // `decode_packet` just delegates to `Decode::decode`, so the only bound the
+    // impl actually needs is `Self: Decode<#lifetime>` -- re-deriving per-param
+    // bounds here would duplicate (and risk drifting from) whatever bound
+    // `derive_decode` already worked out for `Self`, including any
+    // `#[decode(bound = ...)]` / `#[skip]` override.
+    let self_bound: WherePredicate =
+        parse_quote!(Self: ::valence_protocol::__private::Decode<#lifetime>);
 
     let (impl_generics, ty_generics, where_clause) =
-        decode_split_for_impl(input.generics, lifetime.clone());
+        decode_split_for_impl(input.generics, lifetime.clone(), vec![self_bound]);
 
     let name = input.ident;
 
@@ -209,3 +680,303 @@ pub fn derive_decode_packet(item: TokenStream) -> Result<TokenStream> {
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use quote::ToTokens;
+
+    use super::*;
+
+    fn expand(item: TokenStream) -> TokenStream {
+        derive_decode(item).unwrap_or_else(|e| panic!("derive_decode failed: {e}"))
+    }
+
+    fn assert_expands_to_valid_impl(item: TokenStream) -> syn::ItemImpl {
+        let expanded = expand(item);
+        syn::parse2::<syn::ItemImpl>(expanded.clone())
+            .unwrap_or_else(|e| panic!("generated impl failed to parse: {e}\n{expanded}"))
+    }
+
+    /// Strips whitespace so a body check doesn't depend on
+    /// `TokenStream`'s exact (and incidental) token-spacing choices.
+    fn squeeze(ts: &impl ToTokens) -> String {
+        ts.to_token_stream()
+            .to_string()
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect()
+    }
+
+    #[test]
+    fn decode_bound_override_excludes_param_from_generated_bound() {
+        let impl_item = assert_expands_to_valid_impl(quote! {
+            #[decode(bound = "T: ::std::fmt::Debug")]
+            struct Wrapper<T> {
+                marker: ::std::marker::PhantomData<T>,
+            }
+        });
+
+        let where_clause = impl_item
+            .generics
+            .where_clause
+            .to_token_stream()
+            .to_string();
+        assert!(where_clause.contains("Debug"));
+        // The generated `Decode` bound would sit on the parameter itself.
+        let params = impl_item.generics.params.to_token_stream().to_string();
+        assert!(!params.contains("Decode"));
+    }
+
+    #[test]
+    fn decode_packet_requires_self_bound_instead_of_per_param_bound() {
+        let expanded = derive_decode_packet(quote! {
+            #[packet_id = 0]
+            #[decode(bound = "T: ::std::fmt::Debug")]
+            struct Wrapper<T> {
+                marker: ::std::marker::PhantomData<T>,
+            }
+        })
+        .unwrap();
+
+        let packet_item = syn::parse2::<syn::ItemImpl>(expanded.clone())
+            .unwrap_or_else(|e| panic!("generated impl failed to parse: {e}\n{expanded}"));
+
+        let where_clause = packet_item
+            .generics
+            .where_clause
+            .to_token_stream()
+            .to_string();
+        assert!(where_clause.contains("Self"));
+        assert!(!where_clause.contains("T :"));
+    }
+
+    #[test]
+    fn find_tag_type_parses_repr_and_string_literal_forms() {
+        let attrs: Vec<Attribute> = vec![parse_quote!(#[repr(u8)])];
+        assert_eq!(find_tag_type(&attrs).unwrap().unwrap(), "u8");
+
+        let attrs: Vec<Attribute> = vec![parse_quote!(#[tag_type = "u16"])];
+        assert_eq!(find_tag_type(&attrs).unwrap().unwrap(), "u16");
+    }
+
+    #[test]
+    fn find_tag_type_rejects_unsupported_integer_type() {
+        let attrs: Vec<Attribute> = vec![parse_quote!(#[tag_type = "u64"])];
+        assert!(find_tag_type(&attrs).is_err());
+    }
+
+    #[test]
+    fn enum_with_tag_type_decodes_discriminant_as_named_type() {
+        let impl_item = assert_expands_to_valid_impl(quote! {
+            #[tag_type = "u8"]
+            enum Shape {
+                Circle,
+                Square,
+            }
+        });
+
+        let body = impl_item.to_token_stream().to_string();
+        assert!(body.contains("u8"));
+        assert!(!body.contains("VarInt"));
+    }
+
+    #[test]
+    fn named_struct_decode_body_parses_as_valid_block_expression() {
+        // Regression test: `decode_fields` is a sequence of `let` bindings
+        // followed by a trailing `Self { .. }` expression, which is only
+        // valid when wrapped in its own block before being spliced into
+        // `Ok(..)`.
+        assert_expands_to_valid_impl(quote! {
+            struct Plain {
+                a: i32,
+                b: i32,
+            }
+        });
+    }
+
+    #[test]
+    fn named_field_if_condition_decodes_conditionally() {
+        let impl_item = assert_expands_to_valid_impl(quote! {
+            struct Maybe {
+                flag: bool,
+                #[decode(if = "flag")]
+                value: Option<i32>,
+            }
+        });
+
+        let body = impl_item.to_token_stream().to_string();
+        assert!(body.contains("if flag"));
+    }
+
+    #[test]
+    fn decode_if_rejected_on_tuple_struct_field() {
+        let err = derive_decode(quote! {
+            struct Plain(#[decode(if = "true")] i32);
+        })
+        .unwrap_err();
+
+        assert!(err.to_string().contains("named struct fields"));
+    }
+
+    #[test]
+    fn decode_if_rejected_on_enum_variant_field() {
+        let err = derive_decode(quote! {
+            enum E {
+                A(#[decode(if = "true")] i32),
+            }
+        })
+        .unwrap_err();
+
+        assert!(err.to_string().contains("named struct fields"));
+    }
+
+    #[test]
+    fn decode_if_rejected_on_container() {
+        let err = derive_decode(quote! {
+            #[decode(if = "true")]
+            struct Plain {
+                a: i32,
+            }
+        })
+        .unwrap_err();
+
+        assert!(err.to_string().contains("named struct fields"));
+    }
+
+    #[test]
+    fn decode_if_rejected_when_combined_with_skip() {
+        let err = derive_decode(quote! {
+            struct Plain {
+                #[skip]
+                #[decode(if = "true")]
+                a: Option<i32>,
+            }
+        })
+        .unwrap_err();
+
+        assert!(err.to_string().contains("#[skip]"));
+    }
+
+    #[test]
+    fn fallback_unit_variant_handles_unrecognized_discriminant() {
+        let impl_item = assert_expands_to_valid_impl(quote! {
+            enum Mode {
+                A,
+                B,
+                #[fallback]
+                Unknown,
+            }
+        });
+
+        let body = squeeze(&impl_item);
+        assert!(body.contains("_=>Ok(Self::Unknown)"));
+        assert!(!body.contains("bail!"));
+    }
+
+    #[test]
+    fn fallback_single_field_variant_stores_raw_discriminant() {
+        let impl_item = assert_expands_to_valid_impl(quote! {
+            enum Mode {
+                A,
+                #[fallback]
+                Unknown(i32),
+            }
+        });
+
+        let body = squeeze(&impl_item);
+        assert!(body.contains("Self::Unknown(discasi32)"));
+    }
+
+    #[test]
+    fn at_most_one_fallback_variant_allowed() {
+        let err = derive_decode(quote! {
+            enum Mode {
+                #[fallback]
+                A,
+                #[fallback]
+                B,
+            }
+        })
+        .unwrap_err();
+
+        assert!(err.to_string().contains("at most one"));
+    }
+
+    #[test]
+    fn fallback_variant_must_be_unit_or_single_field_tuple() {
+        let err = derive_decode(quote! {
+            enum Mode {
+                #[fallback]
+                Unknown { a: i32, b: i32 },
+            }
+        })
+        .unwrap_err();
+
+        assert!(err.to_string().contains("unit variant or a single-field"));
+    }
+
+    #[test]
+    fn skip_bound_info_excludes_bare_type_param_used_only_by_skipped_field() {
+        let type_params: HashSet<Ident> = [parse_quote!(T)].into_iter().collect();
+        let fields = vec![(true, parse_quote!(T))];
+
+        let (excludes, predicates) = skip_bound_info(&type_params, fields.into_iter());
+
+        assert!(excludes.contains(&parse_quote!(T)));
+        assert_eq!(predicates.len(), 1);
+    }
+
+    #[test]
+    fn skip_bound_info_keeps_type_param_decoded_through_a_container_elsewhere() {
+        // `T` is used bare in a `#[skip]` field, but also appears inside `Vec<T>`
+        // in a normal field -- it must keep its `Decode` bound even though one of
+        // its uses is skipped.
+        let type_params: HashSet<Ident> = [parse_quote!(T)].into_iter().collect();
+        let fields = vec![(true, parse_quote!(T)), (false, parse_quote!(Vec<T>))];
+
+        let (excludes, _) = skip_bound_info(&type_params, fields.into_iter());
+
+        assert!(!excludes.contains(&parse_quote!(T)));
+    }
+
+    #[test]
+    fn skip_bound_info_requires_default_for_type_param_nested_in_array_or_tuple() {
+        let type_params: HashSet<Ident> = [parse_quote!(T)].into_iter().collect();
+        let fields = vec![(true, parse_quote!([T; 4])), (true, parse_quote!((T, T)))];
+
+        let (excludes, predicates) = skip_bound_info(&type_params, fields.into_iter());
+
+        assert!(excludes.contains(&parse_quote!(T)));
+        assert_eq!(predicates.len(), 1);
+    }
+
+    #[test]
+    fn collect_default_idents_does_not_descend_into_generic_containers() {
+        // `Vec<T>: Default` doesn't require `T: Default`, so a `#[skip]` field of
+        // type `Vec<T>` must not produce a `T: Default` bound.
+        let type_params: HashSet<Ident> = [parse_quote!(T)].into_iter().collect();
+        let mut out = HashSet::new();
+
+        collect_default_idents(&parse_quote!(Vec<T>), &type_params, &mut out);
+
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn skip_field_with_array_type_gets_default_bound_in_generated_impl() {
+        let impl_item = assert_expands_to_valid_impl(quote! {
+            struct Grid<T> {
+                #[skip]
+                cells: [T; 16],
+                other: Vec<T>,
+            }
+        });
+
+        let where_clause = squeeze(&impl_item.generics.where_clause);
+        assert!(where_clause.contains("T:::std::default::Default"));
+        // The generated `Decode` bound sits on the parameter itself, not in the
+        // `where` clause.
+        let params = squeeze(&impl_item.generics.params);
+        assert!(params.contains("T:::valence_protocol::Decode"));
+    }
+}
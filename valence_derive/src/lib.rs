@@ -0,0 +1,135 @@
+use std::collections::HashSet;
+
+use proc_macro2::TokenStream;
+use quote::ToTokens;
+use syn::spanned::Spanned;
+use syn::{
+    Error, Expr, ExprLit, GenericParam, Generics, Ident, Lifetime, LifetimeParam, Lit, Meta,
+    Result, Variant, WherePredicate,
+};
+
+mod decode;
+
+#[proc_macro_derive(Decode, attributes(decode, tag_type, fallback, skip))]
+pub fn derive_decode(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    decode::derive_decode(item.into())
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(DecodePacket, attributes(packet_id, decode))]
+pub fn derive_decode_packet(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    decode::derive_decode_packet(item.into())
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Appends `bound` to every type parameter in `generics`, skipping
+/// parameters named in `exclude`. Callers use `exclude` to omit parameters
+/// for which a user-supplied `#[decode(bound = "...")]` predicate already
+/// covers the necessary constraint (or for which no constraint is needed at
+/// all, e.g. a field that is `#[skip]`ped).
+fn add_trait_bounds(generics: &mut Generics, bound: TokenStream, exclude: &HashSet<Ident>) {
+    for param in &mut generics.params {
+        if let GenericParam::Type(type_param) = param {
+            if exclude.contains(&type_param.ident) {
+                continue;
+            }
+            type_param.bounds.push(syn::parse_quote!(#bound));
+        }
+    }
+}
+
+/// Splits `generics` for use in an `impl` block, inserting `lifetime` if the
+/// type doesn't already declare one of its own, and folding
+/// `extra_predicates` into the resulting `where` clause.
+///
+/// `ty_generics` is split off *before* the synthetic lifetime is inserted,
+/// since that lifetime belongs to the `impl<..>` and `where` clause only --
+/// splicing it into the `Self` type path too (e.g. `Plain<'a>` for a
+/// `Plain` with no lifetime of its own) would be a generic-argument-count
+/// mismatch.
+fn decode_split_for_impl(
+    mut generics: Generics,
+    lifetime: Lifetime,
+    extra_predicates: Vec<WherePredicate>,
+) -> (TokenStream, TokenStream, TokenStream) {
+    let ty_generics = generics.clone().split_for_impl().1.to_token_stream();
+
+    if generics.lifetimes().next().is_none() {
+        generics
+            .params
+            .insert(0, GenericParam::Lifetime(LifetimeParam::new(lifetime)));
+    }
+
+    if !extra_predicates.is_empty() {
+        generics
+            .make_where_clause()
+            .predicates
+            .extend(extra_predicates);
+    }
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    (
+        impl_generics.to_token_stream(),
+        ty_generics,
+        where_clause.to_token_stream(),
+    )
+}
+
+/// Finds the `#[packet_id = ...]` helper attribute and parses its integer
+/// literal, if present.
+fn find_packet_id_attr(attrs: &[syn::Attribute]) -> Result<Option<i32>> {
+    for attr in attrs {
+        if attr.path().is_ident("packet_id") {
+            let Meta::NameValue(nv) = &attr.meta else {
+                return Err(Error::new(attr.span(), "expected `#[packet_id = ...]`"));
+            };
+
+            let Expr::Lit(ExprLit {
+                lit: Lit::Int(lit), ..
+            }) = &nv.value
+            else {
+                return Err(Error::new(
+                    nv.value.span(),
+                    "packet ID must be an integer literal",
+                ));
+            };
+
+            return Ok(Some(lit.base10_parse()?));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Pairs each enum variant with its discriminant, following normal Rust
+/// enum discriminant rules: an explicit `= N` resets the counter, otherwise
+/// it continues from the previous variant's discriminant plus one.
+fn pair_variants_with_discriminants(
+    variants: impl Iterator<Item = Variant>,
+) -> Result<Vec<(i32, Variant)>> {
+    let mut next_disc = 0i32;
+
+    variants
+        .map(|variant| {
+            if let Some((_, expr)) = &variant.discriminant {
+                let Expr::Lit(ExprLit {
+                    lit: Lit::Int(lit), ..
+                }) = expr
+                else {
+                    return Err(Error::new(
+                        expr.span(),
+                        "enum discriminant must be an integer literal",
+                    ));
+                };
+                next_disc = lit.base10_parse()?;
+            }
+
+            let disc = next_disc;
+            next_disc += 1;
+            Ok((disc, variant))
+        })
+        .collect()
+}